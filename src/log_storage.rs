@@ -1,15 +1,23 @@
-use std::time::Duration;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use moka::sync::Cache;
+use redis::{Client, Script};
 use uuid::Uuid;
 
-pub trait LogStorage {
-    fn store(&self, attempts: usize, duration: Duration);
+pub trait LogStorage: Send + Sync {
+    // Returns the key's count after storing, so callers that only need a post-store count
+    // (e.g. a sliding-window check) don't have to pay for a second round trip via count().
+    fn store(&self, key: &str, attempts: usize, duration: Duration) -> usize;
 
-    fn count(&self) -> usize;
+    fn count(&self, key: &str) -> usize;
+
+    // Time remaining before the oldest stored entry falls out of the window, or None if
+    // the log is empty. Lets a rate limiter park precisely instead of polling try_acquire.
+    fn time_until_oldest_expires(&self, key: &str) -> Option<Duration>;
 }
 
 pub struct InMemoryLogStorage {
-    cache: Cache<String, usize>,
+    cache: Cache<(String, String), Instant>,
+    duration: Duration,
 }
 
 impl InMemoryLogStorage {
@@ -19,18 +27,123 @@ impl InMemoryLogStorage {
                 .max_capacity(size as u64)
                 .time_to_live(duration)
                 .build(),
+            duration,
         }
     }
 }
 
 impl LogStorage for InMemoryLogStorage {
-    fn store(&self, attempts: usize, duration: Duration) {
+    fn store(&self, key: &str, attempts: usize, duration: Duration) -> usize {
+        let now = Instant::now();
         for _ in 0..attempts {
-            self.cache.insert(Uuid::new_v4().to_string(), 1);
+            self.cache.insert((key.to_string(), Uuid::new_v4().to_string()), now);
         }
+        self.count(key)
+    }
+
+    fn count(&self, key: &str) -> usize {
+        self.cache.iter().filter(|(entry_key, _)| entry_key.0 == key).count()
+    }
+
+    fn time_until_oldest_expires(&self, key: &str) -> Option<Duration> {
+        let oldest = self.cache.iter()
+            .filter(|(entry_key, _)| entry_key.0 == key)
+            .map(|(_, inserted_at)| inserted_at)
+            .min()?;
+        let elapsed = Instant::now().duration_since(oldest);
+        Some(self.duration.saturating_sub(elapsed))
+    }
+}
+
+// Keeps timestamped entries in a Redis sorted set per rate-limit key, so several service
+// replicas enforce one shared limit instead of each getting its own process-local log.
+pub struct RedisLogStorage {
+    client: Client,
+    prefix: String,
+    window: Duration,
+}
+
+// Atomically trims entries that have aged out of the window, adds `attempts` new ones, and
+// refreshes the key's TTL, all in one round trip.
+const STORE_SCRIPT: &str = r#"
+    local zset_key = KEYS[1]
+    local now_ms = tonumber(ARGV[1])
+    local window_ms = tonumber(ARGV[2])
+    local attempts = tonumber(ARGV[3])
+    redis.call('ZREMRANGEBYSCORE', zset_key, '-inf', now_ms - window_ms)
+    for i = 1, attempts do
+        redis.call('ZADD', zset_key, now_ms, now_ms .. '-' .. i .. '-' .. math.random(1, 1000000000))
+    end
+    redis.call('PEXPIRE', zset_key, window_ms)
+    return redis.call('ZCARD', zset_key)
+"#;
+
+// Atomically trims entries that have aged out of the window before counting what remains.
+const COUNT_SCRIPT: &str = r#"
+    local zset_key = KEYS[1]
+    local now_ms = tonumber(ARGV[1])
+    local window_ms = tonumber(ARGV[2])
+    redis.call('ZREMRANGEBYSCORE', zset_key, '-inf', now_ms - window_ms)
+    return redis.call('ZCARD', zset_key)
+"#;
+
+impl RedisLogStorage {
+    pub fn new(redis_url: &str, prefix: &str, window: Duration) -> redis::RedisResult<RedisLogStorage> {
+        Ok(RedisLogStorage {
+            client: Client::open(redis_url)?,
+            prefix: prefix.to_string(),
+            window,
+        })
+    }
+
+    fn zset_key(&self, key: &str) -> String {
+        format!("{}:{}", self.prefix, key)
     }
 
-    fn count(&self) -> usize {
-        self.cache.iter().count()
+    fn now_ms(&self) -> i64 {
+        SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_millis() as i64
+    }
+}
+
+impl LogStorage for RedisLogStorage {
+    fn store(&self, key: &str, attempts: usize, _duration: Duration) -> usize {
+        // A rate limiter must fail closed: if Redis is unreachable we can't know the real
+        // count, so report it as exceeded rather than letting the outage look like an
+        // empty (and therefore wide-open) window.
+        let Ok(mut conn) = self.client.get_connection() else { return usize::MAX; };
+        Script::new(STORE_SCRIPT)
+            .key(self.zset_key(key))
+            .arg(self.now_ms())
+            .arg(self.window.as_millis() as i64)
+            .arg(attempts)
+            .invoke(&mut conn)
+            .unwrap_or(usize::MAX)
     }
-}
\ No newline at end of file
+
+    fn count(&self, key: &str) -> usize {
+        // A rate limiter must fail closed: if Redis is unreachable we can't know the real
+        // count, so report it as exceeded rather than letting the outage look like an
+        // empty (and therefore wide-open) window.
+        let Ok(mut conn) = self.client.get_connection() else { return usize::MAX; };
+        Script::new(COUNT_SCRIPT)
+            .key(self.zset_key(key))
+            .arg(self.now_ms())
+            .arg(self.window.as_millis() as i64)
+            .invoke(&mut conn)
+            .unwrap_or(usize::MAX)
+    }
+
+    fn time_until_oldest_expires(&self, key: &str) -> Option<Duration> {
+        let mut conn = self.client.get_connection().ok()?;
+        let oldest: Vec<(String, i64)> = redis::cmd("ZRANGE")
+            .arg(self.zset_key(key))
+            .arg(0)
+            .arg(0)
+            .arg("WITHSCORES")
+            .query(&mut conn)
+            .ok()?;
+        let (_, oldest_ms) = oldest.into_iter().next()?;
+        let elapsed_ms = (self.now_ms() - oldest_ms).max(0) as u64;
+        Some(self.window.saturating_sub(Duration::from_millis(elapsed_ms)))
+    }
+}
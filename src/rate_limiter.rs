@@ -1,14 +1,50 @@
 use std::collections::HashMap;
-use std::sync::{Arc, Mutex};
+use std::hash::Hash;
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
 use std::time::{Duration, Instant};
 
+use dashmap::DashMap;
+
 use crate::log_storage::LogStorage;
 use crate::scheduler::Scheduler;
 
-trait RateLimiter {
+trait RateLimiter: Send + Sync {
     fn try_acquire(&self, permits: usize) -> bool;
 
     fn get_type(&self) -> RateLimiterType;
+
+    // true once the bucket's window has fully expired and its count is back to zero,
+    // i.e. it is safe for a keyed layer to evict it.
+    fn is_idle(&self) -> bool;
+
+    // Exact time until `permits` could next succeed, used by acquire_blocking to sleep
+    // for precisely that long instead of busy-polling try_acquire.
+    fn time_until_available(&self, permits: usize) -> Duration;
+
+    // Parks until `permits` become available or `timeout` elapses, returning false in the
+    // latter case, instead of forcing the caller to spin on try_acquire.
+    fn acquire_blocking(&self, permits: usize, timeout: Option<Duration>) -> bool {
+        let deadline = timeout.map(|t| Instant::now() + t);
+        loop {
+            if self.try_acquire(permits) {
+                return true;
+            }
+
+            let wait = self.time_until_available(permits);
+            let wait = match deadline {
+                Some(deadline) => {
+                    let now = Instant::now();
+                    if now >= deadline {
+                        return false;
+                    }
+                    std::cmp::min(wait, deadline - now)
+                }
+                None => wait,
+            };
+            thread::sleep(wait);
+        }
+    }
 }
 
 struct RateLimiterFactory {
@@ -34,10 +70,64 @@ impl RateLimiterFactory {
     }
 }
 
+// Wraps any RateLimiter behind a per-key bucket map, so e.g. each client IP gets its own
+// independent counter instead of sharing one global limiter. Idle buckets (window expired,
+// count back to zero) are swept out periodically so unbounded keys don't leak memory.
+// Buckets live in a sharded DashMap rather than one Mutex<HashMap<...>> so lookups for
+// different keys don't serialize behind a single lock.
+pub struct KeyedRateLimiter<K>
+where
+    K: Eq + Hash + Clone + Send + Sync + 'static,
+{
+    buckets: Arc<DashMap<K, Box<dyn RateLimiter>>>,
+    factory: Arc<dyn Fn() -> Box<dyn RateLimiter> + Send + Sync>,
+    cleanup_scheduler: Scheduler,
+}
+
+impl<K> KeyedRateLimiter<K>
+where
+    K: Eq + Hash + Clone + Send + Sync + 'static,
+{
+    pub fn new<F>(cleanup_interval: Duration, factory: F) -> KeyedRateLimiter<K>
+    where
+        F: Fn() -> Box<dyn RateLimiter> + Send + Sync + 'static,
+    {
+        KeyedRateLimiter {
+            buckets: Arc::new(DashMap::new()),
+            factory: Arc::new(factory),
+            cleanup_scheduler: Scheduler::new(cleanup_interval),
+        }
+    }
+
+    pub fn try_acquire_for(&self, key: K, permits: usize) -> bool {
+        self.buckets.entry(key).or_insert_with(|| (self.factory)()).try_acquire(permits)
+    }
+
+    // Public so callers that want their own scheduling for a given key (instead of spinning
+    // on try_acquire_for) can find out exactly how long to wait.
+    pub fn time_until_available_for(&self, key: K, permits: usize) -> Duration {
+        self.buckets.entry(key).or_insert_with(|| (self.factory)()).time_until_available(permits)
+    }
+
+    pub fn start_cleanup(&self) -> bool {
+        let buckets_clone = Arc::clone(&self.buckets);
+        self.cleanup_scheduler.start(move || {
+            buckets_clone.retain(|_, bucket| !bucket.is_idle());
+        })
+    }
+
+    pub fn stop_cleanup(&self) {
+        self.cleanup_scheduler.stop();
+    }
+}
+
 struct TokenBucketRateLimiter {
     rate: Rate,
     permits: Arc<Mutex<usize>>,
+    byte_permits: Arc<Mutex<usize>>,
+    last_refill: Arc<Mutex<Instant>>,
     schedulers: Scheduler,
+    byte_scheduler: Option<Scheduler>,
 }
 
 struct FixedWindowRateLimiter {
@@ -46,31 +136,55 @@ struct FixedWindowRateLimiter {
     window_start: Arc<Mutex<Instant>>,
 }
 
+// The key used by the RateLimiter trait methods, which only ever enforce a single global
+// limit. try_acquire_for lets the same log_storage (e.g. a shared RedisLogStorage) also
+// serve independent per-key limits.
+const GLOBAL_KEY: &str = "global";
+
 struct SlidingWindowLogRateLimiter {
     rate: Rate,
-    log_storage: Arc<Mutex<Box<dyn LogStorage + Send>>>,
+    // LogStorage impls (moka's Cache, a fresh connection per Redis call) are already safe to
+    // call concurrently, so this is just a shared handle, not a lock — different keys (and a
+    // distributed RedisLogStorage's own network round trips) never need to contend with each
+    // other in-process.
+    log_storage: Arc<Box<dyn LogStorage>>,
 }
 
 impl SlidingWindowLogRateLimiter {
-    pub fn new(rate: Rate, log_storage: Box<dyn LogStorage + Send>) -> SlidingWindowLogRateLimiter {
+    pub fn new(rate: Rate, log_storage: Box<dyn LogStorage>) -> SlidingWindowLogRateLimiter {
         SlidingWindowLogRateLimiter {
             rate,
-            log_storage: Arc::new(Mutex::new(log_storage)),
+            log_storage: Arc::new(log_storage),
         }
     }
+
+    // Enforces this limiter's rate independently per key, so one SlidingWindowLogRateLimiter
+    // backed by a distributed log_storage can rate-limit many clients at once.
+    pub fn try_acquire_for(&self, key: &str, permits: usize) -> bool {
+        let count = self.log_storage.store(key, permits, self.rate.duration);
+        count <= self.rate.permit_num
+    }
 }
 
 impl RateLimiter for SlidingWindowLogRateLimiter {
     fn try_acquire(&self, permits: usize) -> bool {
-        let storage = self.log_storage.lock().unwrap();
-        storage.store(permits, self.rate.duration);
-        let count = storage.count();
-        return count <= self.rate.permit_num;
+        self.try_acquire_for(GLOBAL_KEY, permits)
     }
 
     fn get_type(&self) -> RateLimiterType {
         RateLimiterType::SlidingWindowLog
     }
+
+    fn is_idle(&self) -> bool {
+        self.log_storage.count(GLOBAL_KEY) == 0
+    }
+
+    fn time_until_available(&self, permits: usize) -> Duration {
+        if permits > self.rate.permit_num {
+            return Duration::MAX;
+        }
+        self.log_storage.time_until_oldest_expires(GLOBAL_KEY).unwrap_or(Duration::ZERO)
+    }
 }
 
 impl FixedWindowRateLimiter {
@@ -113,30 +227,180 @@ impl RateLimiter for FixedWindowRateLimiter {
     fn get_type(&self) -> RateLimiterType {
         RateLimiterType::FixedWindow
     }
+
+    fn is_idle(&self) -> bool {
+        // The counter only ever gets zeroed by a later try_acquire call, so a key that's
+        // gone quiet never clears it on its own — an expired window is idle regardless.
+        let window_start = self.window_start.lock().unwrap();
+        Instant::now().duration_since(*window_start) > self.rate.duration
+    }
+
+    fn time_until_available(&self, permits: usize) -> Duration {
+        if permits > self.rate.permit_num {
+            return Duration::MAX;
+        }
+        let elapsed = Instant::now().duration_since(*self.window_start.lock().unwrap());
+        self.rate.duration.saturating_sub(elapsed)
+    }
+}
+
+// Fixes the 2x burst FixedWindowRateLimiter allows at window boundaries, without paying
+// SlidingWindowLog's per-permit memory cost: only two counters are kept, and the previous
+// window's count is weighted down as the current window elapses.
+struct SlidingWindowCounterRateLimiter {
+    rate: Rate,
+    previous_window_count: Arc<Mutex<usize>>,
+    current_window_count: Arc<Mutex<usize>>,
+    window_start: Arc<Mutex<Instant>>,
+}
+
+impl SlidingWindowCounterRateLimiter {
+    pub fn new(rate: Rate) -> SlidingWindowCounterRateLimiter {
+        SlidingWindowCounterRateLimiter {
+            rate,
+            previous_window_count: Arc::new(Mutex::new(0)),
+            current_window_count: Arc::new(Mutex::new(0)),
+            window_start: Arc::new(Mutex::new(Instant::now())),
+        }
+    }
+
+    fn roll_window(&self, now: Instant) {
+        let mut window_start = self.window_start.lock().unwrap();
+        let duration = self.rate.duration;
+        let elapsed = now.duration_since(*window_start);
+        if elapsed < duration {
+            return;
+        }
+
+        let windows_elapsed = elapsed.as_secs_f64() / duration.as_secs_f64();
+        let mut previous = self.previous_window_count.lock().unwrap();
+        let mut current = self.current_window_count.lock().unwrap();
+        *previous = if windows_elapsed >= 2.0 { 0 } else { *current };
+        *current = 0;
+        *window_start = now;
+    }
+}
+
+impl RateLimiter for SlidingWindowCounterRateLimiter {
+    fn try_acquire(&self, permits: usize) -> bool {
+        let now = Instant::now();
+        self.roll_window(now);
+
+        let window_start = *self.window_start.lock().unwrap();
+        let duration = self.rate.duration;
+        let elapsed_fraction = (now.duration_since(window_start).as_secs_f64() / duration.as_secs_f64()).clamp(0.0, 1.0);
+
+        let previous = *self.previous_window_count.lock().unwrap() as f64;
+        let mut current = self.current_window_count.lock().unwrap();
+        let estimated = previous * (1.0 - elapsed_fraction) + *current as f64 + permits as f64;
+
+        if estimated > self.rate.permit_num as f64 {
+            return false;
+        }
+
+        *current += permits;
+        true
+    }
+
+    fn get_type(&self) -> RateLimiterType {
+        RateLimiterType::SlidingWindowCounter
+    }
+
+    fn is_idle(&self) -> bool {
+        // current_window_count only gets zeroed inside try_acquire, so a key that fired once
+        // and went quiet would otherwise never look idle — roll the window first, mirroring
+        // the FixedWindow fix. Once rolled, window_start is reset to now, so counts already
+        // being zero (rather than a now-meaningless "expired" check) is what idle means here.
+        self.roll_window(Instant::now());
+        *self.previous_window_count.lock().unwrap() == 0 && *self.current_window_count.lock().unwrap() == 0
+    }
+
+    fn time_until_available(&self, permits: usize) -> Duration {
+        if permits > self.rate.permit_num {
+            return Duration::MAX;
+        }
+        let elapsed = Instant::now().duration_since(*self.window_start.lock().unwrap());
+        let previous = *self.previous_window_count.lock().unwrap() as f64;
+        let current = *self.current_window_count.lock().unwrap() as f64;
+        let room = (self.rate.permit_num - permits) as f64;
+        // The estimate falls as the previous count decays across the window, so find the first
+        // elapsed fraction f with previous * (1 - f) + current <= room. If current alone leaves
+        // no room, nothing can succeed before the window boundary; the roll done by the next
+        // try_acquire restarts the window there, and a call after it solves for the rest.
+        if current <= room {
+            let fraction = if previous > room - current { 1.0 - (room - current) / previous } else { 0.0 };
+            return self.rate.duration.mul_f64(fraction).saturating_sub(elapsed);
+        }
+        self.rate.duration.saturating_sub(elapsed)
+    }
 }
 
 impl TokenBucketRateLimiter {
     pub fn new(rate: Rate) -> TokenBucketRateLimiter {
         let permit_num = rate.permit_num;
         let duration = rate.duration;
-        let scheduler = Scheduler::new(duration);
+        let byte_permit_num = rate.bytes.as_ref().map_or(0, |b| b.permit_num);
+        let byte_scheduler = rate.bytes.as_ref().map(|b| Scheduler::new(b.duration));
         TokenBucketRateLimiter {
             rate,
             permits: Arc::new(Mutex::new(permit_num)),
-            schedulers: scheduler,
+            byte_permits: Arc::new(Mutex::new(byte_permit_num)),
+            last_refill: Arc::new(Mutex::new(Instant::now())),
+            schedulers: Scheduler::new(duration),
+            byte_scheduler,
         }
     }
 
     pub fn start(&self) {
         let permits_clone = Arc::clone(&self.permits); //clone arc, two arcs point to the same memory
+        let last_refill_clone = Arc::clone(&self.last_refill);
         let rate_clone = self.rate.clone();
         self.schedulers.start(move || {
             let mut available_permits = permits_clone.lock().unwrap();
             *available_permits = std::cmp::min(rate_clone.permit_num, *available_permits + rate_clone.permit_num);
+            *last_refill_clone.lock().unwrap() = Instant::now();
         });
 
+        if let (Some(byte_scheduler), Some(bytes_rate)) = (&self.byte_scheduler, &self.rate.bytes) {
+            let byte_permits_clone = Arc::clone(&self.byte_permits);
+            let bytes_permit_num = bytes_rate.permit_num;
+            byte_scheduler.start(move || {
+                let mut available_bytes = byte_permits_clone.lock().unwrap();
+                *available_bytes = std::cmp::min(bytes_permit_num, *available_bytes + bytes_permit_num);
+            });
+        }
+
         return;
     }
+
+    // Dimension used by try_acquire_typed: Ops is always enforced, Bytes only when the
+    // Rate was built with with_bytes — an unconfigured dimension is treated as unlimited.
+    fn bucket_for(&self, token_type: TokenType) -> Option<&Arc<Mutex<usize>>> {
+        match token_type {
+            TokenType::Ops => Some(&self.permits),
+            TokenType::Bytes => self.rate.bytes.as_ref().map(|_| &self.byte_permits),
+        }
+    }
+
+    pub fn try_acquire_typed(&self, ops: usize, bytes: usize) -> bool {
+        let mut available_ops = self.bucket_for(TokenType::Ops).unwrap().lock().unwrap();
+        if *available_ops < ops {
+            return false;
+        }
+
+        let mut available_bytes = self.bucket_for(TokenType::Bytes).map(|bucket| bucket.lock().unwrap());
+        if let Some(available_bytes) = &available_bytes {
+            if **available_bytes < bytes {
+                return false;
+            }
+        }
+
+        *available_ops -= ops;
+        if let Some(mut available_bytes) = available_bytes {
+            *available_bytes -= bytes;
+        }
+        true
+    }
 }
 
 impl RateLimiter for TokenBucketRateLimiter {
@@ -153,12 +417,168 @@ impl RateLimiter for TokenBucketRateLimiter {
     fn get_type(&self) -> RateLimiterType {
         RateLimiterType::TokenBucket
     }
+
+    fn is_idle(&self) -> bool {
+        let ops_idle = *self.permits.lock().unwrap() == self.rate.permit_num;
+        let bytes_idle = match &self.rate.bytes {
+            Some(bytes_rate) => *self.byte_permits.lock().unwrap() == bytes_rate.permit_num,
+            None => true,
+        };
+        ops_idle && bytes_idle
+    }
+
+    fn time_until_available(&self, permits: usize) -> Duration {
+        if permits > self.rate.permit_num {
+            return Duration::MAX;
+        }
+        let elapsed = Instant::now().duration_since(*self.last_refill.lock().unwrap());
+        self.rate.duration.saturating_sub(elapsed)
+    }
+}
+
+// Several handles share one pool of permits and collectively cannot exceed `rate`. Handles
+// are cheap to clone (they just share the Arcs); whichever one is waiting when the shared
+// pool refills gets woken up via refill_notify instead of busy-polling try_acquire.
+pub struct RateLimiterGroup {
+    rate: Rate,
+    permits: Arc<Mutex<usize>>,
+    last_refill: Arc<Mutex<Instant>>,
+    refill_notify: Arc<Condvar>,
+    scheduler: Scheduler,
+}
+
+impl RateLimiterGroup {
+    pub fn new(rate: Rate) -> RateLimiterGroup {
+        let permit_num = rate.permit_num;
+        let duration = rate.duration;
+        RateLimiterGroup {
+            rate,
+            permits: Arc::new(Mutex::new(permit_num)),
+            last_refill: Arc::new(Mutex::new(Instant::now())),
+            refill_notify: Arc::new(Condvar::new()),
+            scheduler: Scheduler::new(duration),
+        }
+    }
+
+    pub fn start(&self) -> bool {
+        let permits_clone = Arc::clone(&self.permits);
+        let last_refill_clone = Arc::clone(&self.last_refill);
+        let refill_notify_clone = Arc::clone(&self.refill_notify);
+        let rate_clone = self.rate.clone();
+        self.scheduler.start(move || {
+            let mut available_permits = permits_clone.lock().unwrap();
+            *available_permits = std::cmp::min(rate_clone.permit_num, *available_permits + rate_clone.permit_num);
+            *last_refill_clone.lock().unwrap() = Instant::now();
+            refill_notify_clone.notify_all();
+        })
+    }
+
+    pub fn handle(&self) -> RateLimiterGroupHandle {
+        RateLimiterGroupHandle {
+            rate: self.rate.clone(),
+            permits: Arc::clone(&self.permits),
+            last_refill: Arc::clone(&self.last_refill),
+            refill_notify: Arc::clone(&self.refill_notify),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct RateLimiterGroupHandle {
+    rate: Rate,
+    permits: Arc<Mutex<usize>>,
+    last_refill: Arc<Mutex<Instant>>,
+    refill_notify: Arc<Condvar>,
+}
+
+impl RateLimiterGroupHandle {
+    // Blocks until either `permits` are available from the shared pool or `timeout` elapses,
+    // parking on refill_notify instead of spinning on try_acquire.
+    pub fn acquire_blocking(&self, permits: usize, timeout: Option<Duration>) -> bool {
+        let mut available = self.permits.lock().unwrap();
+        let deadline = timeout.map(|t| Instant::now() + t);
+        loop {
+            if *available >= permits {
+                *available -= permits;
+                return true;
+            }
+
+            available = match deadline {
+                Some(deadline) => {
+                    let now = Instant::now();
+                    if now >= deadline {
+                        return false;
+                    }
+                    self.refill_notify.wait_timeout(available, deadline - now).unwrap().0
+                }
+                None => self.refill_notify.wait(available).unwrap(),
+            };
+        }
+    }
+
+    // Public so callers that want their own scheduling (instead of going through
+    // acquire_blocking) can find out exactly how long to wait, mirroring acquire_blocking's
+    // own pub-on-the-concrete-type treatment.
+    pub fn time_until_available(&self, permits: usize) -> Duration {
+        if permits > self.rate.permit_num {
+            return Duration::MAX;
+        }
+        let elapsed = Instant::now().duration_since(*self.last_refill.lock().unwrap());
+        self.rate.duration.saturating_sub(elapsed)
+    }
+}
+
+impl RateLimiter for RateLimiterGroupHandle {
+    fn try_acquire(&self, permits: usize) -> bool {
+        let mut available_permits = self.permits.lock().unwrap();
+        if *available_permits < permits {
+            false
+        } else {
+            *available_permits -= permits;
+            true
+        }
+    }
+
+    fn get_type(&self) -> RateLimiterType {
+        RateLimiterType::Group
+    }
+
+    fn is_idle(&self) -> bool {
+        false
+    }
+
+    fn time_until_available(&self, permits: usize) -> Duration {
+        self.time_until_available(permits)
+    }
+
+    // Overrides the trait's busy-sleep default with the real Condvar-based wait, so callers
+    // going through a trait object also get woken up by the shared pool's refill instead of
+    // polling try_acquire.
+    fn acquire_blocking(&self, permits: usize, timeout: Option<Duration>) -> bool {
+        self.acquire_blocking(permits, timeout)
+    }
 }
 
 #[derive(Clone)]
 pub struct Rate {
     permit_num: usize,
     duration: Duration,
+    bytes: Option<ByteRate>,
+}
+
+impl Rate {
+    // Attaches a second, independent "bytes" dimension to this rate. A TokenBucketRateLimiter
+    // built from it then requires both the ops and the bytes bucket to have capacity.
+    pub fn with_bytes(mut self, permit_num: usize, duration: Duration) -> Rate {
+        self.bytes = Some(ByteRate { permit_num, duration });
+        self
+    }
+}
+
+#[derive(Clone)]
+struct ByteRate {
+    permit_num: usize,
+    duration: Duration,
 }
 
 #[derive(Eq, Hash, PartialEq)]
@@ -166,6 +586,16 @@ enum RateLimiterType {
     TokenBucket,
     FixedWindow,
     SlidingWindowLog,
+    SlidingWindowCounter,
+    Group,
+}
+
+// Discriminates which bucket a TokenBucketRateLimiter dimension refers to: the always-present
+// "ops" bucket, or the optional "bytes" bucket configured via Rate::with_bytes.
+#[derive(Eq, Hash, PartialEq, Clone, Copy)]
+enum TokenType {
+    Ops,
+    Bytes,
 }
 
 #[cfg(test)]
@@ -174,7 +604,7 @@ mod tests {
     use std::time::Duration;
 
     use crate::log_storage::InMemoryLogStorage;
-    use crate::rate_limiter::{FixedWindowRateLimiter, Rate, RateLimiter, RateLimiterFactory, RateLimiterType, SlidingWindowLogRateLimiter, TokenBucketRateLimiter};
+    use crate::rate_limiter::{FixedWindowRateLimiter, KeyedRateLimiter, Rate, RateLimiter, RateLimiterFactory, RateLimiterGroup, RateLimiterType, SlidingWindowCounterRateLimiter, SlidingWindowLogRateLimiter, TokenBucketRateLimiter};
 
     #[test]
     fn give_token_bucket_rate_limiter_then_it_protects_the_resource_correctly() {
@@ -182,6 +612,7 @@ mod tests {
         let rate = Rate {
             permit_num: 3,
             duration: Duration::from_secs(5),
+            bytes: None,
         };
         let rate_limiter = TokenBucketRateLimiter::new(rate);
         rate_limiter.start();
@@ -203,6 +634,7 @@ mod tests {
         let rate = Rate {
             permit_num: 5,
             duration: Duration::from_secs(3),
+            bytes: None,
         };
         let rate_limiter = FixedWindowRateLimiter::new(rate);
 
@@ -220,6 +652,7 @@ mod tests {
         let rate = Rate {
             permit_num: 5,
             duration: Duration::from_secs(3),
+            bytes: None,
         };
         let rate_limiter = FixedWindowRateLimiter::new(rate);
         thread::sleep(Duration::from_secs(2));
@@ -236,6 +669,7 @@ mod tests {
         let rate = Rate {
             permit_num: 5,
             duration: Duration::from_secs(3),
+            bytes: None,
         };
         let storage = InMemoryLogStorage::new(rate.permit_num + 1, rate.duration);
         let rate_limiter = SlidingWindowLogRateLimiter::new(rate, Box::new(storage));
@@ -252,6 +686,7 @@ mod tests {
         let rate = Rate {
             permit_num: 5,
             duration: Duration::from_secs(3),
+            bytes: None,
         };
         let log_size = rate.permit_num + 1;
         let duration = rate.duration.clone();
@@ -273,4 +708,201 @@ mod tests {
         thread::sleep(Duration::from_secs(3));
         assert_eq!(rate_limiter.try_acquire(5), true);
     }
+
+    #[test]
+    fn given_keyed_rate_limiter_then_each_key_gets_its_own_independent_bucket() {
+        //given
+        let keyed_rate_limiter: KeyedRateLimiter<String> = KeyedRateLimiter::new(
+            Duration::from_secs(60),
+            || {
+                let rate = Rate {
+                    permit_num: 2,
+                    duration: Duration::from_secs(3),
+                    bytes: None,
+                };
+                Box::new(FixedWindowRateLimiter::new(rate))
+            },
+        );
+
+        //then
+        assert_eq!(keyed_rate_limiter.try_acquire_for("client-a".to_string(), 2), true);
+        assert_eq!(keyed_rate_limiter.try_acquire_for("client-a".to_string(), 1), false);
+        assert_eq!(keyed_rate_limiter.try_acquire_for("client-b".to_string(), 2), true);
+    }
+
+    #[test]
+    fn given_keyed_rate_limiter_when_cleanup_runs_then_idle_buckets_are_evicted() {
+        //given
+        let keyed_rate_limiter: KeyedRateLimiter<String> = KeyedRateLimiter::new(
+            Duration::from_millis(100),
+            || {
+                let rate = Rate {
+                    permit_num: 1,
+                    duration: Duration::from_millis(200),
+                    bytes: None,
+                };
+                Box::new(FixedWindowRateLimiter::new(rate))
+            },
+        );
+        keyed_rate_limiter.start_cleanup();
+
+        //then
+        assert_eq!(keyed_rate_limiter.try_acquire_for("client-a".to_string(), 1), true);
+        assert_eq!(keyed_rate_limiter.buckets.len(), 1);
+
+        thread::sleep(Duration::from_millis(500));
+        keyed_rate_limiter.stop_cleanup();
+        assert_eq!(keyed_rate_limiter.buckets.len(), 0);
+    }
+
+    #[test]
+    fn given_rate_limiter_group_then_handles_collectively_cannot_exceed_the_shared_budget() {
+        //given
+        let rate = Rate {
+            permit_num: 3,
+            duration: Duration::from_secs(5),
+            bytes: None,
+        };
+        let group = RateLimiterGroup::new(rate);
+        let handle_a = group.handle();
+        let handle_b = group.handle();
+
+        //then
+        assert_eq!(handle_a.try_acquire(2), true);
+        assert_eq!(handle_b.try_acquire(2), false);
+        assert_eq!(handle_b.try_acquire(1), true);
+    }
+
+    #[test]
+    fn given_rate_limiter_group_when_budget_refills_then_blocked_handle_is_woken_up() {
+        //given
+        let rate = Rate {
+            permit_num: 2,
+            duration: Duration::from_millis(200),
+            bytes: None,
+        };
+        let group = RateLimiterGroup::new(rate);
+        group.start();
+        let handle = group.handle();
+        assert_eq!(handle.try_acquire(2), true);
+
+        //then
+        assert_eq!(handle.acquire_blocking(2, Some(Duration::from_secs(2))), true);
+    }
+
+    #[test]
+    fn given_token_bucket_rate_limiter_with_bytes_dimension_then_both_buckets_must_have_capacity() {
+        //given
+        let rate = Rate {
+            permit_num: 5,
+            duration: Duration::from_secs(5),
+            bytes: None,
+        }.with_bytes(100, Duration::from_secs(5));
+        let rate_limiter = TokenBucketRateLimiter::new(rate);
+
+        //then
+        assert_eq!(rate_limiter.try_acquire_typed(1, 60), true);
+        assert_eq!(rate_limiter.try_acquire_typed(1, 60), false);
+        assert_eq!(rate_limiter.try_acquire_typed(1, 40), true);
+    }
+
+    #[test]
+    fn given_token_bucket_rate_limiter_without_bytes_dimension_then_bytes_are_unlimited() {
+        //given
+        let rate = Rate {
+            permit_num: 2,
+            duration: Duration::from_secs(5),
+            bytes: None,
+        };
+        let rate_limiter = TokenBucketRateLimiter::new(rate);
+
+        //then
+        assert_eq!(rate_limiter.try_acquire_typed(1, usize::MAX), true);
+        assert_eq!(rate_limiter.try_acquire_typed(1, usize::MAX), true);
+        assert_eq!(rate_limiter.try_acquire_typed(1, usize::MAX), false);
+    }
+
+    #[test]
+    fn given_fixed_window_rate_limiter_when_denied_then_acquire_blocking_waits_for_window_reset() {
+        //given
+        let rate = Rate {
+            permit_num: 2,
+            duration: Duration::from_millis(300),
+            bytes: None,
+        };
+        let rate_limiter = FixedWindowRateLimiter::new(rate);
+        assert_eq!(rate_limiter.try_acquire(2), true);
+
+        //then
+        assert_eq!(rate_limiter.acquire_blocking(2, Some(Duration::from_secs(2))), true);
+    }
+
+    #[test]
+    fn given_fixed_window_rate_limiter_when_denied_then_acquire_blocking_times_out() {
+        //given
+        let rate = Rate {
+            permit_num: 2,
+            duration: Duration::from_secs(10),
+            bytes: None,
+        };
+        let rate_limiter = FixedWindowRateLimiter::new(rate);
+        assert_eq!(rate_limiter.try_acquire(2), true);
+
+        //then
+        assert_eq!(rate_limiter.acquire_blocking(2, Some(Duration::from_millis(200))), false);
+    }
+
+    #[test]
+    fn given_sliding_window_counter_rate_limiter_then_it_protects_the_resource_correctly() {
+        //given
+        let rate = Rate {
+            permit_num: 5,
+            duration: Duration::from_secs(3),
+            bytes: None,
+        };
+        let rate_limiter = SlidingWindowCounterRateLimiter::new(rate);
+
+        //then
+        assert_eq!(rate_limiter.try_acquire(5), true);
+        assert_eq!(rate_limiter.try_acquire(1), false);
+
+        // Two full windows must elapse before the previous window's usage fully decays out
+        // of the estimate (see the boundary-burst test below) — one window isn't enough.
+        thread::sleep(Duration::from_millis(6500));
+        assert_eq!(rate_limiter.try_acquire(5), true);
+    }
+
+    #[test]
+    fn given_sliding_window_counter_rate_limiter_then_it_smooths_the_fixed_window_boundary_burst() {
+        //given
+        let rate = Rate {
+            permit_num: 5,
+            duration: Duration::from_secs(3),
+            bytes: None,
+        };
+        let rate_limiter = SlidingWindowCounterRateLimiter::new(rate);
+        assert_eq!(rate_limiter.try_acquire(5), true);
+
+        //then, right after the window rolls over, the estimated count still carries most of
+        //the previous window's usage so a second full burst is rejected
+        thread::sleep(Duration::from_millis(3050));
+        assert_eq!(rate_limiter.try_acquire(5), false);
+    }
+
+    #[test]
+    fn given_sliding_window_log_rate_limiter_then_each_key_gets_its_own_independent_log() {
+        //given
+        let rate = Rate {
+            permit_num: 5,
+            duration: Duration::from_secs(3),
+            bytes: None,
+        };
+        let storage = InMemoryLogStorage::new((rate.permit_num + 1) * 2, rate.duration);
+        let rate_limiter = SlidingWindowLogRateLimiter::new(rate, Box::new(storage));
+
+        //then
+        assert_eq!(rate_limiter.try_acquire_for("client-a", 5), true);
+        assert_eq!(rate_limiter.try_acquire_for("client-a", 1), false);
+        assert_eq!(rate_limiter.try_acquire_for("client-b", 5), true);
+    }
 }
\ No newline at end of file